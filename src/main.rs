@@ -1,12 +1,22 @@
 use std::{
-    io, thread,
+    io::{self, Read, Write},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
     time::{Duration, Instant},
 };
 
 use clap::Parser;
-use postcard::{to_allocvec, to_allocvec_cobs};
+use postcard::{from_bytes_cobs, to_allocvec, to_allocvec_cobs};
 use serde::{Deserialize, Serialize};
-use serialport::{available_ports, SerialPort, SerialPortType};
+use serialport::{available_ports, SerialPortType};
+
+mod query;
+mod reconnect;
+mod script;
+mod teleop;
+
+use reconnect::{ReconnectConfig, ReconnectingPort};
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -23,9 +33,33 @@ struct Args {
     /// reset
     #[arg(long)]
     reset: bool,
+
+    /// drive the robot live from the keyboard
+    #[arg(long)]
+    teleop: bool,
+
+    /// play back a motion script file
+    #[arg(long)]
+    script: Option<PathBuf>,
+
+    /// query a field from the device and print the response
+    #[arg(long)]
+    get: Option<QueryField>,
+
+    /// USB vendor ID to match when discovering a port, in hex
+    #[arg(long, value_parser = parse_hex_u16, default_value = "16c0")]
+    vid: u16,
+
+    /// USB product ID to match when discovering a port, in hex
+    #[arg(long, value_parser = parse_hex_u16, default_value = "27dd")]
+    pid: u16,
+
+    /// auto-pick the first matching port when more than one is found
+    #[arg(long)]
+    any: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
 struct MotorCommand {
     a: i8,
     b: i8,
@@ -33,7 +67,7 @@ struct MotorCommand {
     d: i8,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 struct LedCommand {
     status: bool,
 }
@@ -44,6 +78,70 @@ enum Command {
     ResetToUsbBoot,
     MotorCommand(MotorCommand),
     LedCommand(LedCommand),
+    Query(QueryKind),
+}
+
+/// A field the host can ask the device for via `Command::Query`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+enum QueryKind {
+    FirmwareVersion,
+    MotorStatus,
+    LedState,
+}
+
+/// The device's answer to a `Command::Query`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+enum Response {
+    FirmwareVersion(String),
+    MotorStatus(MotorCommand),
+    LedState(LedCommand),
+}
+
+/// `--get` values exposed on the CLI, mapped onto the wire-level `QueryKind`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum QueryField {
+    Version,
+    MotorStatus,
+    Led,
+}
+
+impl From<QueryField> for QueryKind {
+    fn from(field: QueryField) -> Self {
+        match field {
+            QueryField::Version => QueryKind::FirmwareVersion,
+            QueryField::MotorStatus => QueryKind::MotorStatus,
+            QueryField::Led => QueryKind::LedState,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct MotorCurrents {
+    a: i16,
+    b: i16,
+    c: i16,
+    d: i16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct EncoderCounts {
+    a: i32,
+    b: i32,
+    c: i32,
+    d: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct FaultFlags {
+    flags: u8,
+}
+
+/// Structured feedback sent by the device on the telemetry stream.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+enum Telemetry {
+    MotorCurrents(MotorCurrents),
+    EncoderCounts(EncoderCounts),
+    FaultFlags(FaultFlags),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -54,30 +152,40 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let port_name = if let Some(port_name) = args.port {
-        port_name
-    } else {
-        find_port()?
+    let explicit_port = args.port.clone();
+    let (vid, pid, any) = (args.vid, args.pid, args.any);
+    // `find_port` may block on an interactive prompt when several VID/PID
+    // candidates match and `--any` wasn't passed. `locate_port` is re-run on
+    // every auto-reconnect attempt (up to `ReconnectConfig::retries` times),
+    // so which candidate the user picked is cached by index and reused on
+    // later reconnects without touching stdin, as long as that index is still
+    // in range for the candidates found; if it isn't, `find_port` prompts
+    // again. The scan itself is never cached, since the device's
+    // path can change when it reenumerates after e.g. `ResetToUsbBoot`.
+    let selected_index = Mutex::new(None::<usize>);
+    let locate_port = move || -> anyhow::Result<String> {
+        if let Some(port_name) = &explicit_port {
+            return Ok(port_name.clone());
+        }
+
+        let mut selected_index = selected_index.lock().unwrap();
+        let (port_name, index) = find_port(vid, pid, any, *selected_index)?;
+        *selected_index = Some(index);
+        Ok(port_name)
     };
 
-    let mut port = serialport::new(port_name, 115200).open()?;
+    let mut port = ReconnectingPort::open(115200, ReconnectConfig::default(), locate_port)?;
+
+    if let Some(field) = args.get {
+        let mut reader = port.reader()?;
+        let response = query::run_query(&mut port, &mut reader, field.into())?;
+        println!("{:?}", response);
+        return Ok(());
+    }
 
     thread::spawn({
-        let mut port = port.try_clone().unwrap();
-        move || loop {
-            let mut text = String::new();
-            match port.read_to_string(&mut text) {
-                Ok(_) => {}
-                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => (),
-                Err(e) => {
-                    eprintln!("{:?}", e);
-                    return;
-                }
-            }
-            if !text.is_empty() {
-                println!("{}", text);
-            }
-        }
+        let reader = port.reader()?;
+        move || read_telemetry_loop(reader)
     });
 
     if args.reset {
@@ -91,6 +199,14 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if args.teleop {
+        return teleop::run_teleop(&mut port);
+    }
+
+    if let Some(script_path) = args.script {
+        return script::run_script(&mut port, &script_path);
+    }
+
     // led on
     port.write_all(&to_allocvec_cobs(&Command::LedCommand(LedCommand {
         status: true,
@@ -112,7 +228,7 @@ fn main() -> anyhow::Result<()> {
 }
 
 #[allow(unused)]
-fn run_motors(port: &mut Box<dyn SerialPort>, drive: i8) -> anyhow::Result<()> {
+fn run_motors(port: &mut ReconnectingPort, drive: i8) -> anyhow::Result<()> {
     let now = Instant::now();
     loop {
         let command = Command::MotorCommand(MotorCommand {
@@ -131,7 +247,7 @@ fn run_motors(port: &mut Box<dyn SerialPort>, drive: i8) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn wind_up_motors(port: &mut Box<dyn SerialPort>, drive: i8) -> anyhow::Result<()> {
+fn wind_up_motors(port: &mut ReconnectingPort, drive: i8) -> anyhow::Result<()> {
     for i in 0..=100 {
         let command = Command::MotorCommand(MotorCommand {
             a: i * drive,
@@ -157,56 +273,149 @@ fn wind_up_motors(port: &mut Box<dyn SerialPort>, drive: i8) -> anyhow::Result<(
     Ok(())
 }
 
-fn list_ports() -> anyhow::Result<()> {
-    let ports = available_ports()?;
-    for port in ports {
-        println!("  {}", port.port_name);
-        match port.port_type {
-            SerialPortType::UsbPort(info) => {
-                println!("    Type: USB");
-                println!("    VID:{:04x} PID:{:04x}", info.vid, info.pid);
-                println!(
-                    "     Serial Number: {}",
-                    info.serial_number.as_ref().map_or("", String::as_str)
-                );
-                println!(
-                    "      Manufacturer: {}",
-                    info.manufacturer.as_ref().map_or("", String::as_str)
-                );
-                println!(
-                    "           Product: {}",
-                    info.product.as_ref().map_or("", String::as_str)
-                );
-                println!(
-                    "         Interface: {}",
-                    info.interface
-                        .as_ref()
-                        .map_or("".to_string(), |x| format!("{:02x}", *x))
-                );
-            }
-            SerialPortType::BluetoothPort => {
-                println!("    Type: Bluetooth");
-            }
-            SerialPortType::PciPort => {
-                println!("    Type: PCI");
+/// Reads raw bytes off `port`, accumulating them into a buffer and splitting
+/// out complete COBS frames on each `0x00` delimiter. Each frame is decoded
+/// as [`Telemetry`]; frames that fail to decode are assumed to be plain
+/// defmt/text log lines and printed as UTF-8 instead.
+fn read_telemetry_loop(mut port: reconnect::ReconnectingReader) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        let read = match port.read(&mut chunk) {
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => continue,
+            Err(e) => {
+                eprintln!("{:?}", e);
+                return;
             }
-            SerialPortType::Unknown => {
-                println!("    Type: Unknown");
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+
+        while let Some(delimiter) = buffer.iter().position(|&b| b == 0x00) {
+            let frame: Vec<u8> = buffer.drain(..=delimiter).collect();
+            let mut decode_buf = frame.clone();
+            match from_bytes_cobs::<Telemetry>(&mut decode_buf) {
+                Ok(telemetry) => println!("{:?}", telemetry),
+                // the COBS decoder needs the trailing 0x00 included, but it's
+                // not part of the text when this falls back to a log line
+                Err(_) => match std::str::from_utf8(&frame[..frame.len() - 1]) {
+                    Ok(text) => print!("{}", text),
+                    Err(_) => eprintln!("Failed to decode frame: {:?}", frame),
+                },
             }
         }
     }
-    Ok(())
 }
 
-fn find_port() -> anyhow::Result<String> {
+fn list_ports() -> anyhow::Result<()> {
     let ports = available_ports()?;
     for port in ports {
-        if let SerialPortType::UsbPort(info) = port.port_type {
+        print_port_info(&port);
+    }
+    Ok(())
+}
+
+fn print_port_info(port: &serialport::SerialPortInfo) {
+    println!("  {}", port.port_name);
+    match &port.port_type {
+        SerialPortType::UsbPort(info) => {
+            println!("    Type: USB");
+            println!("    VID:{:04x} PID:{:04x}", info.vid, info.pid);
+            println!(
+                "     Serial Number: {}",
+                info.serial_number.as_ref().map_or("", String::as_str)
+            );
+            println!(
+                "      Manufacturer: {}",
+                info.manufacturer.as_ref().map_or("", String::as_str)
+            );
+            println!(
+                "           Product: {}",
+                info.product.as_ref().map_or("", String::as_str)
+            );
+            println!(
+                "         Interface: {}",
+                info.interface
+                    .as_ref()
+                    .map_or("".to_string(), |x| format!("{:02x}", *x))
+            );
+        }
+        SerialPortType::BluetoothPort => {
+            println!("    Type: Bluetooth");
+        }
+        SerialPortType::PciPort => {
+            println!("    Type: PCI");
+        }
+        SerialPortType::Unknown => {
+            println!("    Type: Unknown");
+        }
+    }
+}
+
+/// Finds a connected Pico by matching the legacy hand-labeled serial number
+/// or the given USB VID/PID, re-scanning `available_ports` fresh every call
+/// so a device that reenumerated under a new path is still found. If more
+/// than one port matches, `any` picks the first; otherwise, `remembered_index`
+/// is reused if it's still in range, so a reconnect doesn't re-prompt for a
+/// choice already made. Failing that, the candidates are printed (reusing
+/// `print_port_info`) and the user is prompted to pick one by index.
+///
+/// Returns the chosen port's name along with its index among the candidates,
+/// so the caller can remember the choice across future calls.
+fn find_port(
+    vid: u16,
+    pid: u16,
+    any: bool,
+    remembered_index: Option<usize>,
+) -> anyhow::Result<(String, usize)> {
+    let ports = available_ports()?;
+    let candidates: Vec<_> = ports
+        .into_iter()
+        .filter(|port| {
+            let SerialPortType::UsbPort(info) = &port.port_type else {
+                return false;
+            };
             let serial_number = info.serial_number.as_ref().map_or("", String::as_str);
-            if serial_number.eq_ignore_ascii_case("picoplayground") {
-                return Ok(port.port_name);
+            serial_number.eq_ignore_ascii_case("picoplayground")
+                || (info.vid == vid && info.pid == pid)
+        })
+        .collect();
+
+    match candidates.len() {
+        0 => anyhow::bail!("Failed to find port"),
+        1 => Ok((candidates.into_iter().next().unwrap().port_name, 0)),
+        _ if any => Ok((candidates.into_iter().next().unwrap().port_name, 0)),
+        count => {
+            if let Some(index) = remembered_index.filter(|&index| index < count) {
+                return Ok((candidates.into_iter().nth(index).unwrap().port_name, index));
             }
+
+            println!("Multiple matching ports found:");
+            for (index, port) in candidates.iter().enumerate() {
+                println!("[{}]", index);
+                print_port_info(port);
+            }
+            let index = prompt_for_index(count)?;
+            Ok((candidates.into_iter().nth(index).unwrap().port_name, index))
         }
     }
-    anyhow::bail!("Failed to find port")
+}
+
+fn prompt_for_index(count: usize) -> anyhow::Result<usize> {
+    print!("Select a port [0-{}]: ", count - 1);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let index: usize = input
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid selection; rerun with --port <name> or --any"))?;
+    if index >= count {
+        anyhow::bail!("Selection out of range; rerun with --port <name> or --any");
+    }
+    Ok(index)
+}
+
+fn parse_hex_u16(value: &str) -> Result<u16, std::num::ParseIntError> {
+    u16::from_str_radix(value.trim_start_matches("0x"), 16)
 }
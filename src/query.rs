@@ -0,0 +1,61 @@
+use std::io::{ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+use postcard::{from_bytes_cobs, to_allocvec_cobs};
+
+use crate::reconnect::{ReconnectingPort, ReconnectingReader};
+use crate::{Command, QueryKind, Response};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sends a `Command::Query` for `kind` and blocks on `reader`, COBS/postcard
+/// decoding frames until a `Response` of the matching variant arrives or
+/// `QUERY_TIMEOUT` elapses overall. Frames that fail to decode, or decode to
+/// a `Response` variant other than the one asked for, are logged and
+/// skipped rather than treated as fatal, since telemetry and log lines can
+/// interleave with the reply on the wire.
+pub fn run_query(
+    port: &mut ReconnectingPort,
+    reader: &mut ReconnectingReader,
+    kind: QueryKind,
+) -> anyhow::Result<Response> {
+    let bytes = to_allocvec_cobs(&Command::Query(kind))?;
+    port.write_all(&bytes)?;
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 256];
+
+    while Instant::now() < deadline {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(ref e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e.into()),
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+
+        while let Some(delimiter) = buffer.iter().position(|&b| b == 0x00) {
+            let mut frame: Vec<u8> = buffer.drain(..=delimiter).collect();
+            match from_bytes_cobs::<Response>(&mut frame) {
+                Ok(response) if response_matches(&response, kind) => return Ok(response),
+                Ok(other) => eprintln!("Ignoring unexpected response: {:?}", other),
+                // not a Response frame, e.g. a telemetry or log line
+                Err(e) => eprintln!(
+                    "Ignoring frame that failed to decode as a Response: {:?}",
+                    e
+                ),
+            }
+        }
+    }
+
+    anyhow::bail!("Timed out waiting for a response to {:?}", kind)
+}
+
+fn response_matches(response: &Response, kind: QueryKind) -> bool {
+    matches!(
+        (response, kind),
+        (Response::FirmwareVersion(_), QueryKind::FirmwareVersion)
+            | (Response::MotorStatus(_), QueryKind::MotorStatus)
+            | (Response::LedState(_), QueryKind::LedState)
+    )
+}
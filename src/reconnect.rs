@@ -0,0 +1,164 @@
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+/// Tuning knobs for [`ReconnectingPort`]'s reconnect attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectConfig {
+    pub retries: usize,
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        // matches how embedded companion tools tune open retries: the Pico
+        // can take the better part of a second to reenumerate after a reset
+        Self {
+            retries: 50,
+            retry_delay: Duration::from_millis(100),
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Wraps a serial port and transparently reopens it when a write fails
+/// because the device dropped off the bus, e.g. mid-reboot after a
+/// `ResetToUsbBoot` command or a USB cable glitch. `locate` is re-run on
+/// every reconnect attempt so it can rediscover the port if its path
+/// changed on reenumeration.
+///
+/// The underlying port lives behind a shared `Mutex` so that `write_all` can
+/// swap in a freshly reopened handle in place. Readers taken via
+/// [`ReconnectingPort::reader`] don't read through that mutex, though: they
+/// hold their own OS-level duplicate of the port and only touch the shared
+/// mutex briefly, to pick up a fresh duplicate after a reconnect. Otherwise
+/// a blocking read sitting in the mutex for up to `config.timeout` would
+/// stall every write (teleop keystrokes, script ticks) behind it.
+pub struct ReconnectingPort {
+    baud_rate: u32,
+    config: ReconnectConfig,
+    locate: Box<dyn Fn() -> anyhow::Result<String>>,
+    port: Arc<Mutex<Box<dyn SerialPort>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl ReconnectingPort {
+    pub fn open<F>(baud_rate: u32, config: ReconnectConfig, locate: F) -> anyhow::Result<Self>
+    where
+        F: Fn() -> anyhow::Result<String> + 'static,
+    {
+        let locate: Box<dyn Fn() -> anyhow::Result<String>> = Box::new(locate);
+        let port = open_with_retry(baud_rate, &config, locate.as_ref())?;
+        Ok(Self {
+            baud_rate,
+            config,
+            locate,
+            port: Arc::new(Mutex::new(port)),
+            generation: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn reconnect(&self) -> anyhow::Result<()> {
+        eprintln!("Lost connection to device, reconnecting...");
+        let reopened = open_with_retry(self.baud_rate, &self.config, self.locate.as_ref())?;
+        *self.port.lock().unwrap() = reopened;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        eprintln!("Reconnected");
+        Ok(())
+    }
+
+    /// Writes `buf`, transparently reopening the port and retrying once if
+    /// the device dropped off the bus mid-write.
+    pub fn write_all(&mut self, buf: &[u8]) -> anyhow::Result<()> {
+        let result = self.port.lock().unwrap().write_all(buf);
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if is_reconnectable(&e) => {
+                self.reconnect()?;
+                Ok(self.port.lock().unwrap().write_all(buf)?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns a reader with its own OS-level duplicate of the current port,
+    /// so a long blocking read never holds the lock `write_all` needs. The
+    /// reader re-duplicates the port the first time it notices a reconnect
+    /// happened, so it keeps working across one.
+    pub fn reader(&self) -> anyhow::Result<ReconnectingReader> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let local = self.port.lock().unwrap().try_clone()?;
+        Ok(ReconnectingReader {
+            port: Arc::clone(&self.port),
+            generation: Arc::clone(&self.generation),
+            seen_generation: generation,
+            local,
+        })
+    }
+}
+
+/// A read handle duplicated from a [`ReconnectingPort`]'s current
+/// connection. Unlike reading through the port's shared mutex directly,
+/// this holds its own OS-level handle so a blocking read never blocks a
+/// concurrent `write_all`; it only touches the shared mutex, briefly, when
+/// it notices the port was swapped out by a reconnect.
+pub struct ReconnectingReader {
+    port: Arc<Mutex<Box<dyn SerialPort>>>,
+    generation: Arc<AtomicU64>,
+    seen_generation: u64,
+    local: Box<dyn SerialPort>,
+}
+
+impl Read for ReconnectingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let current_generation = self.generation.load(Ordering::SeqCst);
+        if current_generation != self.seen_generation {
+            self.local = self
+                .port
+                .lock()
+                .unwrap()
+                .try_clone()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.seen_generation = current_generation;
+        }
+        self.local.read(buf)
+    }
+}
+
+fn is_reconnectable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe | io::ErrorKind::TimedOut
+    )
+}
+
+fn open_with_retry(
+    baud_rate: u32,
+    config: &ReconnectConfig,
+    locate: &dyn Fn() -> anyhow::Result<String>,
+) -> anyhow::Result<Box<dyn SerialPort>> {
+    let mut last_err = None;
+    for _attempt in 0..config.retries {
+        let opened = locate().and_then(|port_name| {
+            serialport::new(port_name, baud_rate)
+                .timeout(config.timeout)
+                .open()
+                .map_err(anyhow::Error::from)
+        });
+        match opened {
+            Ok(port) => return Ok(port),
+            Err(e) => {
+                last_err = Some(e);
+                thread::sleep(config.retry_delay);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        anyhow::anyhow!("Failed to open port after {} attempts", config.retries)
+    }))
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use postcard::to_allocvec_cobs;
+use serde::Deserialize;
+
+use crate::reconnect::ReconnectingPort;
+use crate::{Command, LedCommand, MotorCommand};
+
+const DEFAULT_TICK_MS: u64 = 20;
+
+#[derive(Debug, Deserialize)]
+struct ScriptFile {
+    #[serde(default = "default_tick_ms")]
+    tick_ms: u64,
+    steps: Vec<Step>,
+}
+
+fn default_tick_ms() -> u64 {
+    DEFAULT_TICK_MS
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+struct Step {
+    motor: [i8; 4],
+    #[serde(default)]
+    led: bool,
+    duration_ms: u64,
+    #[serde(default)]
+    ramp: bool,
+}
+
+impl Step {
+    fn motor_command(&self) -> MotorCommand {
+        MotorCommand {
+            a: self.motor[0],
+            b: self.motor[1],
+            c: self.motor[2],
+            d: self.motor[3],
+        }
+    }
+}
+
+/// Parses a TOML motion script and plays it back against `port`, one timed
+/// step at a time. Steps with `ramp = true` interpolate linearly from the
+/// previous step's motor values to the target over `duration_ms`, ticking at
+/// `tick_ms`; steps without `ramp` jump straight to the target and hold it
+/// for the rest of `duration_ms`. A zeroed `MotorCommand` is always sent when
+/// the script finishes or is interrupted with Ctrl-C.
+pub fn run_script(port: &mut ReconnectingPort, path: &Path) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let script: ScriptFile = toml::from_str(&contents)?;
+    let tick = Duration::from_millis(script.tick_ms.max(1));
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))?;
+    }
+
+    let result = play_steps(port, &script.steps, tick, &interrupted);
+
+    let stop = to_allocvec_cobs(&Command::MotorCommand(MotorCommand::default()))?;
+    port.write_all(&stop)?;
+
+    result
+}
+
+fn play_steps(
+    port: &mut ReconnectingPort,
+    steps: &[Step],
+    tick: Duration,
+    interrupted: &AtomicBool,
+) -> anyhow::Result<()> {
+    let mut previous = MotorCommand::default();
+
+    for step in steps {
+        if interrupted.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let led = to_allocvec_cobs(&Command::LedCommand(LedCommand { status: step.led }))?;
+        port.write_all(&led)?;
+
+        let target = step.motor_command();
+        let duration = Duration::from_millis(step.duration_ms);
+        let start = Instant::now();
+
+        loop {
+            if interrupted.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                break;
+            }
+
+            let command = if step.ramp {
+                interpolate(&previous, &target, elapsed, duration)
+            } else {
+                target.clone()
+            };
+            let bytes = to_allocvec_cobs(&Command::MotorCommand(command))?;
+            port.write_all(&bytes)?;
+
+            thread::sleep(tick.min(duration - elapsed));
+        }
+
+        let bytes = to_allocvec_cobs(&Command::MotorCommand(target.clone()))?;
+        port.write_all(&bytes)?;
+        previous = target;
+    }
+
+    Ok(())
+}
+
+fn interpolate(
+    from: &MotorCommand,
+    to: &MotorCommand,
+    elapsed: Duration,
+    total: Duration,
+) -> MotorCommand {
+    let t = elapsed.as_secs_f64() / total.as_secs_f64();
+    MotorCommand {
+        a: lerp(from.a, to.a, t),
+        b: lerp(from.b, to.b, t),
+        c: lerp(from.c, to.c, t),
+        d: lerp(from.d, to.d, t),
+    }
+}
+
+fn lerp(from: i8, to: i8, t: f64) -> i8 {
+    let value = from as f64 + (to as f64 - from as f64) * t;
+    value.round() as i8
+}
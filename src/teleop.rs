@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use postcard::to_allocvec_cobs;
+
+use crate::reconnect::ReconnectingPort;
+use crate::{Command, LedCommand, MotorCommand};
+
+const TICK_RATE: Duration = Duration::from_millis(50); // 20 Hz
+const DRIVE_SPEED: i8 = 80;
+
+/// Puts the terminal into raw mode and drives the robot live from the
+/// keyboard: WASD for differential drive, space to stop, L to toggle the
+/// LED, and Q or Ctrl-C to leave. The terminal is always restored and the
+/// motors are always sent a final stop command on the way out, whether the
+/// loop exits cleanly or errors.
+pub fn run_teleop(port: &mut ReconnectingPort) -> anyhow::Result<()> {
+    enable_raw_mode()?;
+    let result = teleop_loop(port);
+    disable_raw_mode()?;
+
+    let stop = to_allocvec_cobs(&Command::MotorCommand(MotorCommand::default()))?;
+    port.write_all(&stop)?;
+
+    result
+}
+
+fn teleop_loop(port: &mut ReconnectingPort) -> anyhow::Result<()> {
+    let mut forward: i8 = 0;
+    let mut turn: i8 = 0;
+    let mut led_on = false;
+
+    let mut next_tick = Instant::now();
+    loop {
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('w') | KeyCode::Char('W') => forward = DRIVE_SPEED,
+                    KeyCode::Char('s') | KeyCode::Char('S') => forward = -DRIVE_SPEED,
+                    KeyCode::Char('a') | KeyCode::Char('A') => turn = -DRIVE_SPEED,
+                    KeyCode::Char('d') | KeyCode::Char('D') => turn = DRIVE_SPEED,
+                    KeyCode::Char(' ') => {
+                        forward = 0;
+                        turn = 0;
+                    }
+                    KeyCode::Char('l') | KeyCode::Char('L') => {
+                        led_on = !led_on;
+                        let led =
+                            to_allocvec_cobs(&Command::LedCommand(LedCommand { status: led_on }))?;
+                        port.write_all(&led)?;
+                    }
+                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if Instant::now() >= next_tick {
+            let motor = mix_drive(forward, turn);
+            let bytes = to_allocvec_cobs(&Command::MotorCommand(motor))?;
+            port.write_all(&bytes)?;
+            next_tick += TICK_RATE;
+        }
+    }
+}
+
+/// Mixes forward/turn intent into the four independent motor channels.
+fn mix_drive(forward: i8, turn: i8) -> MotorCommand {
+    let left = forward.saturating_add(turn);
+    let right = forward.saturating_sub(turn);
+    MotorCommand {
+        a: left,
+        b: left,
+        c: right,
+        d: right,
+    }
+}